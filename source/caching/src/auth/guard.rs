@@ -0,0 +1,57 @@
+use crate::api_error::ApiError;
+use crate::auth::jwt::{self, TokenType};
+use crate::user::{Role, User};
+use actix_session::UserSession;
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::HttpRequest;
+use uuid::Uuid;
+
+/// Resolves the signed-in user from the Redis-backed session or, failing
+/// that, a `Bearer` JWT access token, and rejects the request unless their
+/// role is at least as privileged as `required`.
+pub fn require_role(req: &HttpRequest, required: Role) -> Result<User, ApiError> {
+    let user = resolve_identity(req)?;
+
+    if user.role() > required {
+        return Err(ApiError::new(403, "Forbidden"));
+    }
+
+    Ok(user)
+}
+
+/// Resolves the signed-in user and rejects the request unless it's `id`
+/// themself or an admin acting on their behalf.
+pub fn require_self_or_admin(req: &HttpRequest, id: Uuid) -> Result<User, ApiError> {
+    let user = resolve_identity(req)?;
+
+    if user.id != id && user.role() > Role::Admin {
+        return Err(ApiError::new(403, "Forbidden"));
+    }
+
+    Ok(user)
+}
+
+/// Resolves the signed-in `User` from the Redis-backed session or, failing
+/// that, a `Bearer` JWT access token. Shared by `require_role`/
+/// `require_self_or_admin` and by handlers (e.g. `who_am_i`) that just need
+/// the identity without a minimum role.
+pub(crate) fn resolve_identity(req: &HttpRequest) -> Result<User, ApiError> {
+    let session_user_id: Option<Uuid> = req.get_session().get("user_id")?;
+    if let Some(id) = session_user_id {
+        return User::find(id);
+    }
+
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::new(401, "Unauthorized"))?;
+
+    let claims = jwt::validate(token)?;
+    if claims.token_type != TokenType::Access {
+        return Err(ApiError::new(401, "Unauthorized"));
+    }
+
+    User::find(claims.sub)
+}
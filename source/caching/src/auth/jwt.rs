@@ -0,0 +1,137 @@
+use crate::api_error::ApiError;
+use crate::cache;
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use redis::Commands;
+use serde::{Deserialize, Serialize};
+use std::env;
+use uuid::Uuid;
+
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
+
+/// `sub` is always `user.id`, per token pair; `jti` is only populated on
+/// refresh tokens and is the key under which Redis tracks revocation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: Uuid,
+    pub token_type: TokenType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<Uuid>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Pair {
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+fn secret() -> String {
+    env::var("JWT_SECRET").expect("JWT secret not set")
+}
+
+fn sign(claims: &Claims) -> Result<String, ApiError> {
+    encode(&Header::default(), claims, &EncodingKey::from_secret(secret().as_bytes()))
+        .map_err(|e| ApiError::new(500, format!("Failed to sign token: {}", e)))
+}
+
+pub fn validate(token: &str) -> Result<Claims, ApiError> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret().as_bytes()),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
+    .map_err(|_e| ApiError::new(401, "Invalid or expired token"))
+}
+
+fn validate_of_type(token: &str, expected: TokenType) -> Result<Claims, ApiError> {
+    let claims = validate(token)?;
+
+    if claims.token_type != expected {
+        return Err(ApiError::new(401, "Invalid or expired token"));
+    }
+
+    Ok(claims)
+}
+
+/// Issues a fresh access/refresh pair for `user_id` and records the refresh
+/// token's id in Redis so it can later be revoked by `sign_out`.
+pub fn issue_pair(user_id: Uuid) -> Result<Pair, ApiError> {
+    let now = Utc::now();
+
+    let access_token = sign(&Claims {
+        sub: user_id,
+        token_type: TokenType::Access,
+        jti: None,
+        iat: now.timestamp(),
+        exp: (now + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).timestamp(),
+    })?;
+
+    let jti = Uuid::new_v4();
+    let refresh_token = sign(&Claims {
+        sub: user_id,
+        token_type: TokenType::Refresh,
+        jti: Some(jti),
+        iat: now.timestamp(),
+        exp: (now + Duration::days(REFRESH_TOKEN_TTL_DAYS)).timestamp(),
+    })?;
+
+    let mut conn = cache::connection()?;
+    let _: () = conn
+        .set_ex(
+            format!("refresh_token:{}", jti),
+            user_id.to_string(),
+            (REFRESH_TOKEN_TTL_DAYS * 24 * 60 * 60) as usize,
+        )
+        .map_err(|e| ApiError::new(500, format!("Failed to store refresh token: {}", e)))?;
+
+    Ok(Pair {
+        access_token,
+        refresh_token,
+    })
+}
+
+/// Validates a presented refresh token against Redis and, if it hasn't been
+/// revoked, mints a fresh pair without re-checking the password.
+pub fn refresh(refresh_token: &str) -> Result<Pair, ApiError> {
+    let claims = validate_of_type(refresh_token, TokenType::Refresh)?;
+    let jti = claims.jti.ok_or_else(|| ApiError::new(401, "Invalid or expired token"))?;
+    let mut conn = cache::connection()?;
+
+    let key = format!("refresh_token:{}", jti);
+    let user_id: Option<String> = conn
+        .get(&key)
+        .map_err(|e| ApiError::new(500, format!("Failed to read refresh token: {}", e)))?;
+    let user_id = user_id.ok_or_else(|| ApiError::new(401, "Refresh token revoked"))?;
+    let user_id = Uuid::parse_str(&user_id)
+        .map_err(|e| ApiError::new(500, format!("Corrupt refresh token: {}", e)))?;
+
+    let _: () = conn
+        .del(&key)
+        .map_err(|e| ApiError::new(500, format!("Failed to revoke refresh token: {}", e)))?;
+
+    issue_pair(user_id)
+}
+
+/// Invalidates a refresh token ahead of its natural expiry.
+pub fn revoke(refresh_token: &str) -> Result<(), ApiError> {
+    let claims = validate_of_type(refresh_token, TokenType::Refresh)?;
+    let jti = claims.jti.ok_or_else(|| ApiError::new(401, "Invalid or expired token"))?;
+    let mut conn = cache::connection()?;
+
+    let _: () = conn
+        .del(format!("refresh_token:{}", jti))
+        .map_err(|e| ApiError::new(500, format!("Failed to revoke refresh token: {}", e)))?;
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+pub mod guard;
+mod jwt;
+mod routes;
+pub mod totp;
+
+pub use guard::{require_role, require_self_or_admin};
+pub use jwt::{Claims, Pair};
+pub use routes::{
+    enable_totp, forgot_password, init_routes, invite, refresh, register, reset_password,
+    sign_in, sign_out, verify_totp, who_am_i, ForgotPasswordMessage, RefreshMessage,
+    RegistrationMessage, ResetPasswordMessage, SignInMessage, TotpVerifyMessage,
+};
@@ -1,18 +1,27 @@
 
 use crate::api_error::ApiError;
+use crate::auth::jwt;
 use crate::user::{User, UserMessage};
 use crate::email::{ Email, Contact };
 use crate::email_verification_token::{ EmailVerificationToken, EmailVerificationTokenMessage };
 use chrono::Utc;
 use hex;
 use serde::Deserialize;
-use actix_web::{get, post, web, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use actix_session::Session;
 use serde_json::json;
 use uuid::Uuid;
+use utoipa::ToSchema;
+use validator::Validate;
 
+#[utoipa::path(
+    post,
+    path = "/invite",
+    request_body = EmailVerificationTokenMessage,
+    responses((status = 200, description = "Verification email sent"))
+)]
 #[post("/invite")]
-async fn invite(body: web::Json<EmailVerificationTokenMessage>) -> Result<HttpResponse, ApiError> {
+pub async fn invite(body: web::Json<EmailVerificationTokenMessage>) -> Result<HttpResponse, ApiError> {
     let body = body.into_inner();
     let token = EmailVerificationToken::create(body.clone())?;
     let token_string = hex::encode(token.id);
@@ -21,22 +30,34 @@ async fn invite(body: web::Json<EmailVerificationTokenMessage>) -> Result<HttpRe
     .add_recipient(body.email)
     .set_subject("Confirm your email")
     .set_html(format!("Your confirmation code is: {}", &token_string))
-    .send()?;
+    .send()
+    .await?;
 
     Ok(HttpResponse::Ok().json(json!({ "message": "Verification email sent" })))
 }
 
-#[derive(Deserialize)]
-struct RegistrationMessage {
+#[derive(Deserialize, Validate, ToSchema)]
+pub(crate) struct RegistrationMessage {
     token: String,
+    #[validate(email)]
     email: String,
+    #[schema(write_only)]
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
     password: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/register",
+    request_body = RegistrationMessage,
+    responses((status = 200, description = "User registered; body holds a message and the user"), (status = 403, description = "Invalid or expired token"))
+)]
 #[post("/register")]
-async fn register(body: web::Json<RegistrationMessage>) -> Result<HttpResponse, ApiError> {
-    
+pub async fn register(body: web::Json<RegistrationMessage>) -> Result<HttpResponse, ApiError> {
+
     let body = body.into_inner();
+    body.validate().map_err(crate::validation::to_api_error)?;
+
     let token_id = hex::decode(body.token).map_err(|_e| ApiError::new(403, "Invalid token"))?;
     
     let token = EmailVerificationToken::find(&token_id)
@@ -60,9 +81,26 @@ async fn register(body: web::Json<RegistrationMessage>) -> Result<HttpResponse,
     Ok(HttpResponse::Ok().json(json!({ "message": "Successlly registered", "user": user })))
 }
 
+#[derive(Deserialize, Validate, ToSchema)]
+pub(crate) struct SignInMessage {
+    #[validate(email)]
+    email: String,
+    #[schema(write_only)]
+    password: String,
+    totp_code: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/sign-in",
+    request_body = SignInMessage,
+    responses((status = 200, description = "Signed in; body holds the user and an access/refresh token pair"), (status = 401, description = "Invalid credentials or missing 2FA code"))
+)]
 #[post("/sign-in")]
-async fn sign_in(credentials: web::Json<UserMessage>, session: Session) -> Result<HttpResponse, ApiError> {
+pub async fn sign_in(credentials: web::Json<SignInMessage>, session: Session) -> Result<HttpResponse, ApiError> {
     let credentials = credentials.into_inner();
+    credentials.validate().map_err(crate::validation::to_api_error)?;
+
     let user = User::find_by_email(credentials.email)
         .map_err(|e| {
             match e.status_code {
@@ -71,45 +109,233 @@ async fn sign_in(credentials: web::Json<UserMessage>, session: Session) -> Resul
             }
         })?;
     let is_valid = user.verify_password(credentials.password.as_bytes())?;
-    if is_valid == true {
-        session.set("user_id", user.id)?;
-        session.renew();
+    if !is_valid {
+        return Err(ApiError::new(401, "Credentials not valid!".to_string()));
+    }
+
+    if user.totp_enforced() {
+        let code = credentials
+            .totp_code
+            .ok_or_else(|| ApiError::new(401, "2FA code required"))?;
+
+        if !user.verify_totp(&code)? {
+            return Err(ApiError::new(401, "2FA code invalid"));
+        }
+    }
+
+    session.set("user_id", user.id)?;
+    session.renew();
+
+    let pair = jwt::issue_pair(user.id)?;
+
+    Ok(HttpResponse::Ok()
+        .header("x-refresh-token", pair.refresh_token.clone())
+        .json(json!({ "user": user, "access_token": pair.access_token, "refresh_token": pair.refresh_token })))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct TotpVerifyMessage {
+    code: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/2fa/enable",
+    responses((status = 200, description = "TOTP secret and recovery codes issued"))
+)]
+#[post("/2fa/enable")]
+pub async fn enable_totp(session: Session) -> Result<HttpResponse, ApiError> {
+    let id: Uuid = session
+        .get("user_id")?
+        .ok_or_else(|| ApiError::new(401, "Unauthorized"))?;
+
+    let (user, recovery_codes) = User::enable_totp(id)?;
+    let secret = user.totp_secret.clone().expect("just set by enable_totp");
+    let uri = crate::auth::totp::provisioning_uri("Learning-Rust-Web", &user.email, &secret);
+
+    Ok(HttpResponse::Ok().json(json!({ "otpauth_uri": uri, "recovery_codes": recovery_codes })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/2fa/verify",
+    request_body = TotpVerifyMessage,
+    responses((status = 200, description = "2FA enrollment confirmed"), (status = 401, description = "Invalid code"))
+)]
+#[post("/2fa/verify")]
+pub async fn verify_totp(session: Session, body: web::Json<TotpVerifyMessage>) -> Result<HttpResponse, ApiError> {
+    let id: Uuid = session
+        .get("user_id")?
+        .ok_or_else(|| ApiError::new(401, "Unauthorized"))?;
+
+    let user = User::find(id)?;
+    let now = Utc::now().timestamp() as u64;
+    let valid = user
+        .totp_secret
+        .as_deref()
+        .map_or(false, |secret| crate::auth::totp::verify_code(secret, &body.code, now));
+    if valid {
+        User::confirm_totp(id)?;
+        Ok(HttpResponse::Ok().json(json!({ "message": "2FA enrollment confirmed" })))
+    } else {
+        Err(ApiError::new(401, "2FA code invalid"))
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct RefreshMessage {
+    refresh_token: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/refresh",
+    request_body = RefreshMessage,
+    responses((status = 200, description = "Fresh access/refresh pair"), (status = 401, description = "Missing or revoked refresh token"))
+)]
+#[post("/refresh")]
+pub async fn refresh(req: HttpRequest, body: Option<web::Json<RefreshMessage>>) -> Result<HttpResponse, ApiError> {
+    let refresh_token = body
+        .map(|b| b.into_inner().refresh_token)
+        .or_else(|| {
+            req.headers()
+                .get("x-refresh-token")
+                .and_then(|h| h.to_str().ok())
+                .map(String::from)
+        })
+        .ok_or_else(|| ApiError::new(401, "Missing refresh token"))?;
+
+    let pair = jwt::refresh(&refresh_token)?;
+
+    Ok(HttpResponse::Ok().json(json!({ "access_token": pair.access_token, "refresh_token": pair.refresh_token })))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct ForgotPasswordMessage {
+    email: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/forgot-password",
+    request_body = ForgotPasswordMessage,
+    responses((status = 200, description = "Reset email sent if the address is registered"))
+)]
+#[post("/forgot-password")]
+pub async fn forgot_password(body: web::Json<ForgotPasswordMessage>) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+
+    // Do the same DB + SMTP work whether or not the address is registered
+    // (only the email content differs), so response latency can't be used
+    // to enumerate accounts.
+    let user_exists = User::find_by_email(body.email.clone()).is_ok();
 
-        Ok(HttpResponse::Ok().json(user))
+    let token = EmailVerificationToken::create(EmailVerificationTokenMessage { email: body.email.clone() })?;
+    let token_string = hex::encode(token.id);
+
+    let html = if user_exists {
+        format!("Your password reset code is: {}", &token_string)
     } else {
-        Err(ApiError::new(401, "Credentials not valid!".to_string()))
+        "We received a password reset request for this address, but no account exists for it.".to_string()
+    };
+
+    Email::new(Contact::new("v56b87@gmail.com", "chapin666"))
+        .add_recipient(body.email)
+        .set_subject("Reset your password")
+        .set_html(html)
+        .send()
+        .await?;
+
+    Ok(HttpResponse::Ok().json(json!({ "message": "If that email is registered, a reset link has been sent" })))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct ResetPasswordMessage {
+    token: String,
+    email: String,
+    #[schema(write_only)]
+    password: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/reset-password",
+    request_body = ResetPasswordMessage,
+    responses((status = 200, description = "Password reset; body holds a message and the user"), (status = 403, description = "Invalid or expired token"))
+)]
+#[post("/reset-password")]
+pub async fn reset_password(body: web::Json<ResetPasswordMessage>) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+    let token_id = hex::decode(body.token).map_err(|_e| ApiError::new(403, "Invalid token"))?;
+
+    let token = EmailVerificationToken::find(&token_id)
+        .map_err(|e| {
+            match e.status_code {
+                404 => ApiError::new(403, "Invalid token"),
+                _ => e,
+            }
+        })?;
+
+    if token.email != body.email {
+        return Err(ApiError::new(403, "Invalid token"));
     }
+
+    if token.expires_at < Utc::now().naive_utc() {
+        return Err(ApiError::new(403, "Token expired"));
+    }
+
+    let user = User::find_by_email(body.email)?;
+    let user = User::update_password(user.id, body.password)?;
+
+    EmailVerificationToken::delete(&token_id)?;
+
+    Ok(HttpResponse::Ok().json(json!({ "message": "Password reset", "user": user })))
 }
 
+#[utoipa::path(
+    post,
+    path = "/sign-out",
+    responses((status = 200, description = "Signed out"), (status = 401, description = "Unauthorized"))
+)]
 #[post("/sign-out")]
-async fn sign_out(session: Session) -> Result<HttpResponse, ApiError> {
+pub async fn sign_out(req: HttpRequest, session: Session) -> Result<HttpResponse, ApiError> {
     let id: Option<Uuid> = session.get("user_id")?;
 
     if let Some(_) = id {
         session.purge();
+
+        if let Some(refresh_token) = req.headers().get("x-refresh-token").and_then(|h| h.to_str().ok()) {
+            jwt::revoke(refresh_token)?;
+        }
+
         Ok(HttpResponse::Ok().json(json!({ "message": "Successfully signed out" })))
     } else {
         Err(ApiError::new(401, "Unauthorized".to_string()))
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/who-am-i",
+    responses((status = 200, description = "The signed-in user", body = User), (status = 401, description = "Unauthorized"))
+)]
 #[get("/who-am-i")]
-async fn who_am_i(session: Session) -> Result<HttpResponse, ApiError> {
-    let id: Option<Uuid> = session.get("user_id")?;
+pub async fn who_am_i(req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    let user = super::guard::resolve_identity(&req)?;
 
-    if let Some(id) = id {
-        let user = User::find(id)?;
-        Ok(HttpResponse::Ok().json(user))
-    } else {
-        Err(ApiError::new(401, "Unauthorized".to_string()))
-    }
-} 
+    Ok(HttpResponse::Ok().json(user))
+}
 
 
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(invite);
     cfg.service(register);
     cfg.service(sign_in);
+    cfg.service(refresh);
+    cfg.service(enable_totp);
+    cfg.service(verify_totp);
+    cfg.service(forgot_password);
+    cfg.service(reset_password);
     cfg.service(sign_out);
     cfg.service(who_am_i);
 }
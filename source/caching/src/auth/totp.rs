@@ -0,0 +1,58 @@
+use hmac::{Hmac, Mac, NewMac};
+use rand::RngCore;
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+
+type HmacSha1 = Hmac<Sha1>;
+
+const PERIOD_SECONDS: u64 = 30;
+const DIGITS: u32 = 6;
+
+/// Generates a random 20-byte TOTP secret, base32-encoded for display/QR use.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base32::encode(base32::Alphabet::RFC4648 { padding: false }, &bytes)
+}
+
+/// Builds the `otpauth://totp/...` URI consumed by authenticator apps.
+pub fn provisioning_uri(issuer: &str, email: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{email}?secret={secret}&issuer={issuer}&period={period}&digits={digits}",
+        issuer = issuer,
+        email = email,
+        secret = secret,
+        period = PERIOD_SECONDS,
+        digits = DIGITS,
+    )
+}
+
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let bytes: [u8; 4] = hash[offset..offset + 4].try_into().expect("4 byte window");
+    let value = u32::from_be_bytes(bytes) & 0x7fff_ffff;
+
+    value % 10_u32.pow(DIGITS)
+}
+
+/// Verifies a 6-digit code against the secret, tolerating one step of clock
+/// drift on either side (`T-1`, `T`, `T+1`), comparing in constant time.
+pub fn verify_code(secret_base32: &str, code: &str, unix_time: u64) -> bool {
+    let secret = match base32::decode(base32::Alphabet::RFC4648 { padding: false }, secret_base32) {
+        Some(bytes) => bytes,
+        None => return false,
+    };
+
+    let step = unix_time / PERIOD_SECONDS;
+
+    [step.wrapping_sub(1), step, step + 1]
+        .iter()
+        .any(|&counter| {
+            let expected = format!("{:0width$}", hotp(&secret, counter), width = DIGITS as usize);
+            expected.as_bytes().ct_eq(code.as_bytes()).into()
+        })
+}
@@ -0,0 +1,15 @@
+use crate::api_error::ApiError;
+use redis::Connection;
+use std::env;
+
+pub fn connection() -> Result<Connection, ApiError> {
+    let host = env::var("REDIS_HOST").expect("Redis host not set");
+    let port = env::var("REDIS_PORT").expect("Redis port not set");
+
+    let client = redis::Client::open(format!("redis://{}:{}", host, port))
+        .map_err(|e| ApiError::new(500, format!("Failed to open redis client: {}", e)))?;
+
+    client
+        .get_connection()
+        .map_err(|e| ApiError::new(500, format!("Failed to get redis connection: {}", e)))
+}
@@ -0,0 +1,123 @@
+use crate::api_error::ApiError;
+use lettre::message::{Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::env;
+
+pub struct Contact {
+    address: String,
+    name: String,
+}
+
+impl Contact {
+    pub fn new(address: &str, name: &str) -> Self {
+        Contact {
+            address: address.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn into_mailbox(self) -> Result<Mailbox, ApiError> {
+        format!("{} <{}>", self.name, self.address)
+            .parse()
+            .map_err(|e| ApiError::new(500, format!("Invalid email address: {}", e)))
+    }
+}
+
+pub struct Email {
+    from: Contact,
+    recipients: Vec<String>,
+    subject: String,
+    html: Option<String>,
+    text: Option<String>,
+}
+
+impl Email {
+    pub fn new(from: Contact) -> Self {
+        Email {
+            from,
+            recipients: Vec::new(),
+            subject: String::new(),
+            html: None,
+            text: None,
+        }
+    }
+
+    pub fn add_recipient(mut self, recipient: String) -> Self {
+        self.recipients.push(recipient);
+        self
+    }
+
+    pub fn set_subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = subject.into();
+        self
+    }
+
+    pub fn set_html(mut self, html: impl Into<String>) -> Self {
+        self.html = Some(html.into());
+        self
+    }
+
+    pub fn set_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Sends the message through an async SMTP transport that `transport()`
+    /// builds from the `SMTP_*` env vars on every call (no transport is kept
+    /// between sends), attaching both the HTML body and a plaintext
+    /// alternative when both are set.
+    pub async fn send(self) -> Result<(), ApiError> {
+        let mut builder = Message::builder().from(self.from.into_mailbox()?).subject(self.subject);
+
+        for recipient in &self.recipients {
+            builder = builder.to(recipient
+                .parse()
+                .map_err(|e| ApiError::new(500, format!("Invalid recipient address: {}", e)))?);
+        }
+
+        let body = match (self.text, self.html) {
+            (Some(text), Some(html)) => MultiPart::alternative()
+                .singlepart(SinglePart::plain(text))
+                .singlepart(SinglePart::html(html)),
+            (None, Some(html)) => MultiPart::mixed().singlepart(SinglePart::html(html)),
+            (Some(text), None) => MultiPart::mixed().singlepart(SinglePart::plain(text)),
+            (None, None) => MultiPart::mixed().singlepart(SinglePart::plain(String::new())),
+        };
+
+        let message = builder
+            .multipart(body)
+            .map_err(|e| ApiError::new(500, format!("Failed to build email: {}", e)))?;
+
+        transport()?
+            .send(message)
+            .await
+            .map_err(|e| ApiError::new(500, format!("Failed to send email: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+fn transport() -> Result<AsyncSmtpTransport<Tokio1Executor>, ApiError> {
+    let host = env::var("SMTP_HOST").expect("SMTP host not set");
+    let port: u16 = env::var("SMTP_PORT")
+        .expect("SMTP port not set")
+        .parse()
+        .map_err(|e| ApiError::new(500, format!("Invalid SMTP_PORT: {}", e)))?;
+    let security = env::var("SMTP_SECURITY").unwrap_or_else(|_| "none".to_string());
+
+    let mut builder = match security.as_str() {
+        "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+            .map_err(|e| ApiError::new(500, format!("Failed to build SMTP transport: {}", e)))?,
+        "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+            .map_err(|e| ApiError::new(500, format!("Failed to build SMTP transport: {}", e)))?,
+        _ => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+    }
+    .port(port);
+
+    if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+        builder = builder.credentials(Credentials::new(username, password));
+    }
+
+    Ok(builder.build())
+}
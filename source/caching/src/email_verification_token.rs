@@ -0,0 +1,65 @@
+use crate::api_error::ApiError;
+use crate::db;
+use crate::schema::email_verification_token;
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+const TOKEN_TTL_MINUTES: i64 = 30;
+
+#[derive(Clone, Deserialize, Serialize, ToSchema)]
+pub struct EmailVerificationTokenMessage {
+    pub email: String,
+}
+
+#[derive(Queryable, Insertable, Serialize, ToSchema)]
+#[table_name = "email_verification_token"]
+pub struct EmailVerificationToken {
+    pub id: Vec<u8>,
+    pub email: String,
+    pub expires_at: NaiveDateTime,
+}
+
+impl EmailVerificationToken {
+    pub fn create(message: EmailVerificationTokenMessage) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+
+        let mut id = vec![0u8; 32];
+        rand::thread_rng().fill(id.as_mut_slice());
+
+        let token = EmailVerificationToken {
+            id,
+            email: message.email,
+            expires_at: Utc::now().naive_utc() + Duration::minutes(TOKEN_TTL_MINUTES),
+        };
+
+        let token = diesel::insert_into(email_verification_token::table)
+            .values(token)
+            .get_result(&conn)?;
+
+        Ok(token)
+    }
+
+    pub fn find(id: &[u8]) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+
+        let token = email_verification_token::table
+            .filter(email_verification_token::id.eq(id))
+            .first(&conn)?;
+
+        Ok(token)
+    }
+
+    /// Deletes the token so it can't be replayed, used by `/reset-password`
+    /// once a reset backed by it has succeeded.
+    pub fn delete(id: &[u8]) -> Result<usize, ApiError> {
+        let conn = db::connection()?;
+
+        let res = diesel::delete(email_verification_token::table.filter(email_verification_token::id.eq(id)))
+            .execute(&conn)?;
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,23 @@
+table! {
+    email_verification_token (id) {
+        id -> Bytea,
+        email -> Varchar,
+        expires_at -> Timestamp,
+    }
+}
+
+table! {
+    user (id) {
+        id -> Uuid,
+        email -> Varchar,
+        password -> Varchar,
+        totp_secret -> Nullable<Varchar>,
+        totp_recover -> Nullable<Jsonb>,
+        totp_confirmed -> Bool,
+        role -> Integer,
+        avatar -> Nullable<Bytea>,
+        avatar_content_type -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
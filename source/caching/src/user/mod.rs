@@ -4,4 +4,5 @@ mod routes;
 pub use model::User;
 pub use model::UserMessage;
 pub use model::Params;
-pub use routes::init_routes;
\ No newline at end of file
+pub use model::Role;
+pub use routes::{delete, find, find_all, find_avatar, init_routes, update, upload_avatar};
\ No newline at end of file
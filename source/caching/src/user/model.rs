@@ -0,0 +1,371 @@
+
+use crate::api_error::ApiError;
+use crate::db;
+use crate::schema::user;
+use crate::db::LoadPaginated;
+use crate::{sort_by, filter};
+use chrono::{ NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use argon2::Config;
+use rand::Rng;
+use hex;
+use subtle::ConstantTimeEq;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+
+
+#[derive(Serialize, Deserialize, AsChangeset, ToSchema, Validate)]
+#[table_name = "user"]
+pub struct UserMessage {
+    #[validate(email)]
+    pub email: String,
+    #[schema(write_only)]
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub password: String,
+}
+
+#[derive(Deserialize, Serialize, Queryable, Insertable, ToSchema)]
+#[table_name = "user"]
+pub struct User {
+    pub id: Uuid,
+    pub email: String,
+    #[serde(skip_serializing)]
+    #[schema(write_only)]
+    pub password: String,
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>,
+    #[serde(skip_serializing)]
+    pub totp_recover: Option<serde_json::Value>,
+    #[serde(skip_serializing)]
+    pub totp_confirmed: bool,
+    pub role: i32,
+    #[serde(skip_serializing)]
+    #[schema(write_only)]
+    pub avatar: Option<Vec<u8>>,
+    #[serde(skip_serializing)]
+    pub avatar_content_type: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+const AVATAR_DIMENSION: u32 = 256;
+const ALLOWED_AVATAR_FORMATS: [image::ImageFormat; 3] = [
+    image::ImageFormat::Png,
+    image::ImageFormat::Jpeg,
+    image::ImageFormat::WebP,
+];
+
+/// Privilege level stored on `User::role` as a small int (`Admin` = 0 is the
+/// most privileged), lowest value wins when comparing against a minimum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Role {
+    Admin = 0,
+    Moderator = 1,
+    User = 2,
+}
+
+impl From<i32> for Role {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => Role::Admin,
+            1 => Role::Moderator,
+            _ => Role::User,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema, IntoParams)]
+pub struct Params {
+
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    pub email: Option<String>,
+    pub sort_by: Option<String>,
+
+    #[serde(rename = "created_at[gte]")]
+    pub created_at_gte: Option<NaiveDateTime>,
+
+    #[serde(rename = "created_at[lte]")]
+    pub created_at_lte: Option<NaiveDateTime>,
+
+    #[serde(rename = "updated_at[gte]")]
+    pub updated_at_gte: Option<NaiveDateTime>,
+
+    #[serde(rename = "updated_at[lte]")]
+    pub updated_at_lte: Option<NaiveDateTime>,
+}
+
+impl User {
+
+    pub fn find_by_email(email: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+
+        let user = user::table
+            .filter(user::email.eq(email))
+            .first(&conn)?;
+
+        Ok(user)
+    }
+
+    pub fn find_all(params: Params) -> Result<(Vec<Self>, i64), ApiError> {
+        let conn = db::connection()?;
+        let mut query = user::table.into_boxed();
+
+        query = filter!(query,
+            (user::email, @like, params.email),
+            (user::created_at, @ge, params.created_at_gte),
+            (user::created_at, @le, params.created_at_lte),
+            (user::updated_at, @ge, params.updated_at_gte),
+            (user::updated_at, @le, params.updated_at_lte)
+        );
+
+        query = sort_by!(query, params.sort_by,
+            ("id", user::id),
+            ("email", user::email),
+            ("created_at", user::created_at),
+            ("updated_at", user::updated_at)
+        );
+
+        let (users, total_pages) = query.load_with_pagination(&conn, params.page, params.page_size)?;
+        Ok((users, total_pages))
+    }
+
+    pub fn find(id: Uuid) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+
+        let user = user::table
+            .filter(user::id.eq(id))
+            .first::<User>(&conn)?;
+
+        Ok(user)
+    }
+
+    pub fn create(user: UserMessage) -> Result<Self, ApiError> {
+        user.validate().map_err(crate::validation::to_api_error)?;
+
+        let conn = db::connection()?;
+
+        let mut user = User::from(user);
+        user.hash_passsword()?;
+
+        let user = diesel::insert_into(user::table)
+            .values(user)
+            .get_result(&conn)
+            .map_err(|e| match &e {
+                diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, info)
+                    if info.constraint_name() == Some("user_email_key") =>
+                {
+                    ApiError::new(409, "Email already exists")
+                }
+                _ => ApiError::from(e),
+            })?;
+
+        Ok(user)
+    }
+
+    pub fn update(id: Uuid, mut user: UserMessage) -> Result<Self, ApiError> {
+        user.validate().map_err(crate::validation::to_api_error)?;
+
+        let conn = db::connection()?;
+
+        let salt: [u8; 32] = rand::thread_rng().gen();
+        let config = Config::default();
+        user.password = argon2::hash_encoded(user.password.as_bytes(), &salt, &config)
+            .map_err(|e| ApiError::new(500, format!("Failed to hash password: {}", e)))?;
+
+        let user = diesel::update(user::table)
+            .filter(user::id.eq(id))
+            .set(user)
+            .get_result::<User>(&conn)?;
+
+        Ok(user)
+    }
+
+    pub fn delete(id: Uuid) -> Result<usize, ApiError> {
+        let conn = db::connection()?;
+
+        let res = diesel::delete(
+                user::table
+                    .filter(user::id.eq(id))
+            )
+            .execute(&conn)?;
+
+        Ok(res)
+    }
+
+    /// Hashes `password` and writes only the `password` column, used by the
+    /// `/reset-password` flow so unrelated fields are left untouched.
+    pub fn update_password(id: Uuid, password: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+
+        let mut user = User::find(id)?;
+        user.password = password;
+        user.hash_passsword()?;
+
+        let user = diesel::update(user::table)
+            .filter(user::id.eq(id))
+            .set(user::password.eq(user.password))
+            .get_result::<User>(&conn)?;
+
+        Ok(user)
+    }
+
+    /// Decodes `bytes`, validates it's a PNG/JPEG/WebP, downscales it to a
+    /// `AVATAR_DIMENSION`x`AVATAR_DIMENSION` thumbnail (preserving aspect
+    /// ratio) and stores the re-encoded PNG alongside its content type.
+    pub fn update_avatar(id: Uuid, bytes: &[u8]) -> Result<Self, ApiError> {
+        let format = image::guess_format(bytes)
+            .map_err(|_e| ApiError::new(400, "Unrecognized image format"))?;
+
+        if !ALLOWED_AVATAR_FORMATS.contains(&format) {
+            return Err(ApiError::new(400, "Avatar must be PNG, JPEG, or WebP"));
+        }
+
+        let image = image::load_from_memory_with_format(bytes, format)
+            .map_err(|e| ApiError::new(400, format!("Failed to decode image: {}", e)))?;
+
+        let thumbnail = if image.width() > AVATAR_DIMENSION || image.height() > AVATAR_DIMENSION {
+            image.thumbnail(AVATAR_DIMENSION, AVATAR_DIMENSION)
+        } else {
+            image
+        };
+
+        let mut encoded = Vec::new();
+        thumbnail
+            .write_to(&mut std::io::Cursor::new(&mut encoded), image::ImageOutputFormat::Png)
+            .map_err(|e| ApiError::new(500, format!("Failed to encode avatar: {}", e)))?;
+
+        let conn = db::connection()?;
+        let user = diesel::update(user::table)
+            .filter(user::id.eq(id))
+            .set((
+                user::avatar.eq(Some(encoded)),
+                user::avatar_content_type.eq(Some("image/png".to_string())),
+            ))
+            .get_result::<User>(&conn)?;
+
+        Ok(user)
+    }
+
+    pub fn hash_passsword(&mut self) -> Result<(), ApiError> {
+        let salt: [u8; 32] = rand::thread_rng().gen();
+        let config = Config::default();
+
+        self.password = argon2::hash_encoded(self.password.as_bytes(), &salt, &config)
+            .map_err(|e| ApiError::new(500, format!("Failed to hash password: {}", e)))?;
+
+        Ok(())
+    }
+
+    pub fn verify_password(&self, password: &[u8]) -> Result<bool, ApiError> {
+        argon2::verify_encoded(&self.password, password)
+            .map_err(|e| ApiError::new(500, format!("Failed to verify password: {}", e)))
+    }
+
+    pub fn role(&self) -> Role {
+        Role::from(self.role)
+    }
+
+    /// Generates a new TOTP secret and a set of single-use recovery codes,
+    /// persisting both so the next `/2fa/verify` call can confirm enrollment.
+    pub fn enable_totp(id: Uuid) -> Result<(Self, Vec<String>), ApiError> {
+        let conn = db::connection()?;
+
+        let secret = crate::auth::totp::generate_secret();
+        let recovery_codes: Vec<String> = (0..10)
+            .map(|_| hex::encode(rand::thread_rng().gen::<[u8; 5]>()))
+            .collect();
+
+        let user = diesel::update(user::table)
+            .filter(user::id.eq(id))
+            .set((
+                user::totp_secret.eq(Some(secret)),
+                user::totp_recover.eq(Some(serde_json::to_value(&recovery_codes).unwrap())),
+                // A freshly (re-)issued secret is unconfirmed until `/2fa/verify`
+                // proves the caller can actually produce a valid code for it.
+                user::totp_confirmed.eq(false),
+            ))
+            .get_result::<User>(&conn)?;
+
+        Ok((user, recovery_codes))
+    }
+
+    /// Marks 2FA as confirmed, making `sign_in` start requiring a code.
+    /// Called only after `/2fa/verify` accepts a code produced for the
+    /// not-yet-confirmed secret.
+    pub fn confirm_totp(id: Uuid) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+
+        let user = diesel::update(user::table)
+            .filter(user::id.eq(id))
+            .set(user::totp_confirmed.eq(true))
+            .get_result::<User>(&conn)?;
+
+        Ok(user)
+    }
+
+    /// Returns whether 2FA is active and confirmed, i.e. `sign_in` must
+    /// require a code. Verifying a code before confirmation (enrollment)
+    /// does not go through this gate.
+    pub fn totp_enforced(&self) -> bool {
+        self.totp_secret.is_some() && self.totp_confirmed
+    }
+
+    /// Verifies a submitted 6-digit TOTP code, falling back to a one-time
+    /// recovery code (which is then removed from `totp_recover`).
+    pub fn verify_totp(&self, code: &str) -> Result<bool, ApiError> {
+        let now = Utc::now().timestamp() as u64;
+
+        if let Some(secret) = &self.totp_secret {
+            if crate::auth::totp::verify_code(secret, code, now) {
+                return Ok(true);
+            }
+        }
+
+        let recovery_codes: Vec<String> = self
+            .totp_recover
+            .as_ref()
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+
+        if let Some(pos) = recovery_codes
+            .iter()
+            .position(|c| c.as_bytes().ct_eq(code.as_bytes()).into())
+        {
+            let conn = db::connection()?;
+            let mut remaining = recovery_codes;
+            remaining.remove(pos);
+
+            diesel::update(user::table)
+                .filter(user::id.eq(self.id))
+                .set(user::totp_recover.eq(Some(serde_json::to_value(&remaining).unwrap())))
+                .execute(&conn)?;
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+}
+
+
+impl From<UserMessage> for User {
+    fn from(user: UserMessage) -> Self {
+        User {
+            id: Uuid::new_v4(),
+            email: user.email,
+            password: user.password,
+            totp_secret: None,
+            totp_recover: None,
+            totp_confirmed: false,
+            role: Role::User as i32,
+            avatar: None,
+            avatar_content_type: None,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+        }
+    }
+}
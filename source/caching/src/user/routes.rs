@@ -0,0 +1,132 @@
+use crate::api_error::ApiError;
+use crate::auth::{require_role, require_self_or_admin};
+use crate::user::{Params, Role, User, UserMessage};
+use actix_multipart::Multipart;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use futures::{StreamExt, TryStreamExt};
+use serde_json::json;
+use uuid::Uuid;
+
+/// Caps the buffered upload well above the 256x256 thumbnail the image is
+/// downscaled to, so a client can't stream an unbounded body into memory
+/// before `image::guess_format` ever runs.
+const MAX_AVATAR_UPLOAD_BYTES: usize = 8 * 1024 * 1024;
+
+#[utoipa::path(
+    get,
+    path = "/users",
+    params(Params),
+    responses((status = 200, description = "List of users", body = [User]), (status = 403, description = "Admin role required"))
+)]
+#[get("/users")]
+pub async fn find_all(req: HttpRequest, params: web::Query<Params>) -> Result<HttpResponse, ApiError> {
+    require_role(&req, Role::Admin)?;
+
+    let (users, total_pages) = User::find_all(params.into_inner())?;
+
+    Ok(HttpResponse::Ok()
+        .header("x-total-pages", total_pages.to_string())
+        .json(users))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}",
+    responses((status = 200, description = "The user", body = User), (status = 404, description = "User not found"))
+)]
+#[get("/users/{id}")]
+pub async fn find(id: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    let user = User::find(id.into_inner())?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/{id}",
+    request_body = UserMessage,
+    responses((status = 200, description = "The updated user", body = User), (status = 403, description = "Not self or admin"))
+)]
+#[put("/users/{id}")]
+pub async fn update(req: HttpRequest, id: web::Path<Uuid>, user: web::Json<UserMessage>) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    require_self_or_admin(&req, id)?;
+
+    let user = User::update(id, user.into_inner())?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{id}",
+    responses((status = 200, description = "User deleted"), (status = 403, description = "Admin role required"))
+)]
+#[delete("/users/{id}")]
+pub async fn delete(req: HttpRequest, id: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    require_role(&req, Role::Admin)?;
+
+    User::delete(id.into_inner())?;
+
+    Ok(HttpResponse::Ok().json(json!({ "message": "User deleted" })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/avatar",
+    responses((status = 200, description = "Avatar stored", body = User), (status = 400, description = "Unsupported or undecodable image"))
+)]
+#[post("/users/{id}/avatar")]
+pub async fn upload_avatar(req: HttpRequest, id: web::Path<Uuid>, mut payload: Multipart) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    require_self_or_admin(&req, id)?;
+
+    let mut bytes = web::BytesMut::new();
+
+    while let Some(mut field) = payload
+        .try_next()
+        .await
+        .map_err(|e| ApiError::new(400, format!("Invalid multipart body: {}", e)))?
+    {
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| ApiError::new(400, format!("Invalid multipart body: {}", e)))?;
+
+            if bytes.len() + chunk.len() > MAX_AVATAR_UPLOAD_BYTES {
+                return Err(ApiError::new(400, "Avatar upload too large"));
+            }
+
+            bytes.extend_from_slice(&chunk);
+        }
+    }
+
+    let user = User::update_avatar(id, &bytes)?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/avatar",
+    responses((status = 200, description = "The stored avatar image"), (status = 404, description = "No avatar set"))
+)]
+#[get("/users/{id}/avatar")]
+pub async fn find_avatar(id: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    let user = User::find(id.into_inner())?;
+
+    let avatar = user.avatar.ok_or_else(|| ApiError::new(404, "No avatar set"))?;
+    let content_type = user.avatar_content_type.unwrap_or_else(|| "image/png".to_string());
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .header("cache-control", "public, max-age=86400")
+        .body(avatar))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(find_all);
+    cfg.service(find);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(upload_avatar);
+    cfg.service(find_avatar);
+}
@@ -0,0 +1,10 @@
+use crate::api_error::ApiError;
+use validator::ValidationErrors;
+
+/// Flattens a `validator` failure into the structured 4xx the rest of the
+/// crate uses, naming every field that failed instead of a generic message.
+pub fn to_api_error(errors: ValidationErrors) -> ApiError {
+    let fields: Vec<String> = errors.field_errors().keys().map(|field| field.to_string()).collect();
+
+    ApiError::new(422, format!("Validation failed for fields: {}", fields.join(", ")))
+}